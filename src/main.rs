@@ -6,21 +6,27 @@ use clap::{
 };
 use owo_colors::OwoColorize;
 use rustyline::{config::Configurer, highlight::Highlighter, Completer, Helper, Hinter, Validator};
+use std::collections::HashMap;
 use std::future::poll_fn;
 use std::ops::DerefMut;
-use std::task::Poll;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
 use std::{
     borrow::Cow::{self, Borrowed},
     io,
     process::exit,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 use std::io::Error;
-use tokio::io::ReadBuf;
+use tokio::io::{Interest, ReadBuf};
 use tokio::{
     io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
-    net::TcpStream,
+    net::{TcpListener, TcpStream, UdpSocket, UnixStream},
     sync::RwLock,
 };
 
@@ -40,9 +46,11 @@ const STYLES: Styles = Styles::styled()
 #[derive(Parser, Debug)]
 #[command(version, about, verbatim_doc_comment, styles = STYLES, name = "scpi")]
 struct Args {
-    /// The host to connect to.
-    #[arg()]
-    host: String,
+    /// The host to connect to. Prefix with `unix://`, or give an absolute
+    /// path, to connect to a Unix domain socket instead of TCP. Not used
+    /// with `--listen`.
+    #[arg(required_unless_present = "listen")]
+    host: Option<String>,
 
     /// The port to use.
     #[arg(default_value = "9001")]
@@ -55,6 +63,79 @@ struct Args {
     /// A command/query to run and immediately exit.
     #[arg(short)]
     command: Option<String>,
+
+    /// The transport to speak to the instrument over.
+    #[arg(long, value_enum, default_value = "tcp")]
+    transport: TransportKind,
+
+    /// Seconds of idle time before the first TCP keepalive probe is sent.
+    #[arg(long, default_value = "4")]
+    keepalive_secs: u64,
+
+    /// Seconds between subsequent TCP keepalive probes.
+    #[arg(long, default_value = "1")]
+    keepalive_interval: u64,
+
+    /// Disable TCP keepalive entirely.
+    #[arg(long)]
+    no_keepalive: bool,
+
+    /// Disable Nagle's algorithm (TCP_NODELAY) for lower-latency command bursts.
+    #[arg(long)]
+    nodelay: bool,
+
+    /// Seconds to linger on unsent data at disconnect (SO_LINGER). Omit to use the OS default.
+    #[arg(long)]
+    linger: Option<u64>,
+
+    /// Run as a mock SCPI instrument listening on this port instead of
+    /// connecting out as a client.
+    #[arg(long)]
+    listen: Option<u16>,
+
+    /// Seed file of `COMMAND=RESPONSE` pairs for `--listen` mode.
+    #[arg(long)]
+    responses: Option<PathBuf>,
+}
+
+/// TCP socket-level tuning knobs, independent of the SCPI protocol itself.
+/// Ignored for the Unix and UDP transports.
+struct TcpTuning {
+    keepalive: Option<KeepaliveTuning>,
+    nodelay: bool,
+    linger: Option<Duration>,
+}
+
+struct KeepaliveTuning {
+    secs: u64,
+    interval: u64,
+}
+
+impl From<&Args> for TcpTuning {
+    fn from(args: &Args) -> Self {
+        let keepalive = if args.no_keepalive {
+            None
+        } else {
+            Some(KeepaliveTuning {
+                secs: args.keepalive_secs,
+                interval: args.keepalive_interval,
+            })
+        };
+
+        return TcpTuning {
+            keepalive,
+            nodelay: args.nodelay,
+            linger: args.linger.map(Duration::from_secs),
+        };
+    }
+}
+
+/// Which transport `main` hands the session to: `Tcp` goes to `run` (TCP,
+/// or a Unix domain socket if the host names one), `Udp` to `run_udp`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum TransportKind {
+    Tcp,
+    Udp,
 }
 
 #[derive(Completer, Helper, Hinter, Validator)]
@@ -76,6 +157,128 @@ impl Highlighter for HighlightPrompt {
     }
 }
 
+/// The underlying connection to the instrument. Abstracts over the
+/// transports the client can speak so `write_cmd`/`read_until_terminator`
+/// don't need to care which one is in use.
+enum Connection {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Connection::Tcp(s) => Pin::new(s).poll_read(cx, buf),
+            Connection::Unix(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Connection::Tcp(s) => Pin::new(s).poll_write(cx, buf),
+            Connection::Unix(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Connection::Tcp(s) => Pin::new(s).poll_flush(cx),
+            Connection::Unix(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Connection::Tcp(s) => Pin::new(s).poll_shutdown(cx),
+            Connection::Unix(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Determine if `host` names a Unix domain socket rather than a TCP host.
+///
+/// Recognizes a `unix://` scheme prefix, or a bare path starting with `/`.
+///
+/// # Returns
+///
+/// The socket path, if `host` names one.
+fn unix_socket_path(host: &str) -> Option<&str> {
+    if let Some(path) = host.strip_prefix("unix://") {
+        return Some(path);
+    }
+
+    if host.starts_with('/') {
+        return Some(host);
+    }
+
+    return None;
+}
+
+/// Opens the connection to the instrument, picking a transport based on
+/// `hostname`: a Unix domain socket if it looks like one, TCP otherwise.
+/// For TCP, also returns a second handle on the same socket (a `dup`'d
+/// file descriptor) for the heartbeat to peek from, so it never has to
+/// share the `Connection`'s `RwLock` with the foreground read/write path.
+async fn connect(
+    hostname: &str,
+    port: u16,
+    tuning: &TcpTuning,
+) -> anyhow::Result<(Connection, Option<TcpStream>)> {
+    if let Some(path) = unix_socket_path(hostname) {
+        let connection = UnixStream::connect(path)
+            .await
+            .with_context(|| format!("Failed to connect to Unix socket {}", path))?;
+
+        return Ok((Connection::Unix(connection), None));
+    }
+
+    let connection: TcpStream = TcpStream::connect((hostname, port)).await?;
+
+    // Ugly hack to set socket-level options on the tokio TcpStream.
+    let connection = connection.into_std()?;
+    connection.set_nonblocking(false)?;
+    let socket = socket2::Socket::from(connection);
+
+    if let Some(keepalive) = &tuning.keepalive {
+        let probe = socket2::TcpKeepalive::new()
+            .with_time(Duration::from_secs(keepalive.secs))
+            .with_interval(Duration::from_secs(keepalive.interval));
+
+        #[cfg(not(windows))]
+        let probe = probe.with_retries(4);
+
+        socket.set_tcp_keepalive(&probe)?;
+    }
+
+    socket.set_nodelay(tuning.nodelay)?;
+
+    if let Some(linger) = tuning.linger {
+        socket.set_linger(Some(linger))?;
+    }
+
+    let connection: std::net::TcpStream = socket.into();
+
+    // Dup the fd before handing the original off, so the heartbeat gets its
+    // own independent stream over the same socket.
+    let peek_handle = connection.try_clone()?;
+
+    // Turn the std connections back into tokio streams now that socket options are set.
+    let connection = TcpStream::from_std(connection)?;
+    let peek_handle = TcpStream::from_std(peek_handle)?;
+
+    return Ok((Connection::Tcp(connection), Some(peek_handle)));
+}
+
 /// Determine if a command string is a query or not.
 /// # Arguments
 ///
@@ -122,6 +325,28 @@ where
     return Ok(buffer);
 }
 
+/// Appends a trailing newline to `command`, unless it already has one.
+fn terminate_command(command: &str) -> String {
+    let mut cmd_copy = command.to_owned();
+    if !cmd_copy.ends_with('\n') {
+        cmd_copy.push('\n');
+    }
+    return cmd_copy;
+}
+
+/// Turns a raw query response into `write_cmd`/`write_cmd_datagram`'s
+/// return shape: trims it on success, or prints and swallows the error so
+/// a single bad query doesn't take down the whole session.
+fn finish_query_response(response: anyhow::Result<String>) -> anyhow::Result<Option<String>> {
+    return match response {
+        Ok(text) => Ok(Some(text.trim().to_owned())),
+        Err(err) => {
+            eprintln!("{}", err);
+            Ok(None)
+        }
+    };
+}
+
 /// Sends `command` to the supplied buffer and returns the query result, if any.
 async fn write_cmd<T>(
     connection: &mut T,
@@ -132,92 +357,143 @@ where
     T: AsyncWrite + AsyncRead + Unpin,
 {
     let is_query_cmd = is_query(command);
-    let mut cmd_copy = command.to_owned();
 
-    if !cmd_copy.ends_with('\n') {
-        cmd_copy.push('\n');
+    connection
+        .write_all(terminate_command(command).as_bytes())
+        .await?;
+
+    if is_query_cmd {
+        return finish_query_response(read_until_terminator(connection, timeout).await);
     }
 
-    connection.write_all(cmd_copy.as_bytes()).await?;
+    return Ok(None);
+}
 
-    if is_query_cmd {
-        let response = read_until_terminator(connection, timeout).await;
+/// Sends `command` as a single datagram and returns the query result, if any.
+///
+/// UDP has no stream framing, so unlike [`write_cmd`] this reads back at
+/// most one datagram rather than scanning for a newline.
+async fn write_cmd_datagram(
+    socket: &UdpSocket,
+    command: &str,
+    timeout: u64,
+) -> anyhow::Result<Option<String>> {
+    let is_query_cmd = is_query(command);
 
-        return match response {
-            Ok(text) => Ok(Some(text.trim().to_owned())),
-            Err(err) => {
-                eprintln!("{}", err);
-                Ok(None)
-            }
-        };
+    socket.send(terminate_command(command).as_bytes()).await?;
+
+    if is_query_cmd {
+        return finish_query_response(read_datagram(socket, timeout).await);
     }
 
     return Ok(None);
 }
 
-async fn try_peek(stream: &TcpStream, buf: &mut ReadBuf<'_>) -> Result<usize, Error> {
-    let mut pending = true;
+/// Waits for a single response datagram and returns it, if one arrives before `timeout`.
+async fn read_datagram(socket: &UdpSocket, timeout: u64) -> anyhow::Result<String> {
+    let timeout_length = Duration::from_secs(timeout);
+    let mut buffer = vec![0u8; 65536];
 
-    return poll_fn(|cx| {
-        let status = stream.poll_peek(cx, buf);
+    let size = tokio::time::timeout(timeout_length, socket.recv(&mut buffer))
+        .await
+        .context("Timed out waiting for query response")??;
 
-        pending = status.is_pending();
+    return Ok(String::from_utf8_lossy(&buffer[..size]).into_owned());
+}
 
-        // Lie to the poll function so it doesn't block.
-        if pending {
-            return Poll::Ready(Ok(1));
-        }
+/// Waits until `stream` reports readable, then performs a single
+/// non-destructive peek to tell a genuine half-close apart from incoming
+/// data that's simply waiting to be consumed by [`read_until_terminator`].
+///
+/// Unlike the old polling hack, this never has to lie to `poll_fn` about
+/// pending reads: by the time we peek, `ready` has already told us the
+/// socket won't block.
+async fn poll_closed(stream: &TcpStream) -> Result<bool, Error> {
+    stream.ready(Interest::READABLE).await?;
 
-        return status;
-    })
-    .await;
+    let mut buf = [0u8; 1];
+    let mut rb = ReadBuf::new(&mut buf);
+
+    let size = poll_fn(|cx| stream.poll_peek(cx, &mut rb)).await?;
+
+    return Ok(size == 0);
 }
 
-fn start_heartbeat(connection: Arc<RwLock<TcpStream>>, interval: Duration) {
-    tokio::spawn(async move {
-        let mut buf = [0u8; 1];
-        let mut rb = ReadBuf::new(&mut buf);
+/// How long the heartbeat sleeps before retrying while a query is in
+/// flight and it isn't safe to touch the socket at all.
+const HEARTBEAT_BACKOFF: Duration = Duration::from_millis(50);
 
+/// Watches `stream` for a half-close and exits the process when it sees one.
+///
+/// `stream` must be an independent handle (e.g. a `dup`'d fd) on the same
+/// socket as the foreground connection, *not* the foreground connection
+/// itself guarded by a lock: `ready(Interest::READABLE)` can block
+/// indefinitely on an idle instrument, and holding that connection's lock
+/// across an indefinite await would deadlock the first write the
+/// foreground task tries to make. `query_in_flight` lets the foreground
+/// side tell us a response is expected, so we don't race
+/// `read_until_terminator` for the same bytes.
+fn start_heartbeat(stream: TcpStream, query_in_flight: Arc<AtomicBool>) {
+    tokio::spawn(async move {
         loop {
-            // Inner scope to unlock the stream before sleeping.
-            {
-                let conn = connection.read().await;
-
-                let size = try_peek(&conn, &mut rb).await;
+            if query_in_flight.load(Ordering::Acquire) {
+                tokio::time::sleep(HEARTBEAT_BACKOFF).await;
+                continue;
+            }
 
-                // If we were ready and saw a 0 byte read, connection closed or socket keepalive failed.
-                if size.unwrap_or(1) == 0 {
+            match poll_closed(&stream).await {
+                Ok(true) => {
                     println!("\nConnection lost.");
                     crossterm::terminal::disable_raw_mode().expect("Failed to disable raw mode");
                     exit(1);
                 }
+                Ok(false) => {
+                    // A query may have started while we were parked on
+                    // `ready`; leave its response for `read_until_terminator`.
+                    if query_in_flight.load(Ordering::Acquire) {
+                        continue;
+                    }
+
+                    // Readable but not closed, and no query is in flight to
+                    // claim these bytes -- nothing else will ever consume
+                    // them, so `ready` would fire forever. Drain and discard
+                    // the unsolicited data instead so the socket goes quiet
+                    // and the next loop genuinely blocks on `ready` again.
+                    let mut discard = [0u8; 4096];
+
+                    match stream.try_read(&mut discard) {
+                        Ok(0) => {
+                            // The peer closed between our peek and this read.
+                            println!("\nConnection lost.");
+                            crossterm::terminal::disable_raw_mode()
+                                .expect("Failed to disable raw mode");
+                            exit(1);
+                        }
+                        Ok(_) => {}
+                        Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            return;
+                        }
+                    }
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    return;
+                }
             }
-
-            // Only poll every 5 seconds to avoid extra work.
-            tokio::time::sleep(interval).await;
         }
     });
 }
 
-async fn run(hostname: &str, port: u16, command: Option<&str>, timeout: u64) -> GenericResult {
-    let connection: TcpStream = TcpStream::connect((hostname, port)).await?;
-
-    // Ugly hack to set the keepalive property of the tokio TcpStream.
-    let connection = connection.into_std()?;
-    connection.set_nonblocking(false)?;
-    let socket = socket2::Socket::from(connection);
-    let keepalive = socket2::TcpKeepalive::new()
-        .with_time(Duration::from_secs(4))
-        .with_interval(Duration::from_secs(1));
-    
-    #[cfg(!windows)]
-    let keepalive = keepalive.with_retries(4);
-
-    socket.set_tcp_keepalive(&keepalive)?;
-    let connection: std::net::TcpStream = socket.into();
-
-    // Turn the std connection back into a tokio stream now that keepalive is enabled.
-    let connection = TcpStream::from_std(connection)?;
+async fn run(
+    hostname: &str,
+    port: u16,
+    command: Option<&str>,
+    timeout: u64,
+    tuning: &TcpTuning,
+) -> GenericResult {
+    let (connection, peek_handle) = connect(hostname, port, tuning).await?;
 
     let mut wrapped = Arc::new(RwLock::new(connection));
 
@@ -250,7 +526,11 @@ async fn run(hostname: &str, port: u16, command: Option<&str>, timeout: u64) ->
 
     // Spawn a separate task that will poll the stream for whether it's closed.
     // Do this since the main task is stuck waiting for a readline.
-    start_heartbeat(wrapped.clone(), Duration::from_secs(5));
+    let query_in_flight = Arc::new(AtomicBool::new(false));
+
+    if let Some(peek_handle) = peek_handle {
+        start_heartbeat(peek_handle, query_in_flight.clone());
+    }
 
     // Enter the input loop.
     loop {
@@ -265,7 +545,11 @@ async fn run(hostname: &str, port: u16, command: Option<&str>, timeout: u64) ->
 
         rl.add_history_entry(&input)?;
 
+        // Tell the heartbeat we're waiting on a response of our own, so it
+        // leaves those bytes for us instead of draining them.
+        query_in_flight.store(is_query(&input), Ordering::Release);
         let response = write_cmd(wrapped.write().await.deref_mut(), &input, timeout).await?;
+        query_in_flight.store(false, Ordering::Release);
 
         if let Some(resp) = response {
             println!("{}", resp);
@@ -273,23 +557,210 @@ async fn run(hostname: &str, port: u16, command: Option<&str>, timeout: u64) ->
     }
 }
 
+/// Interactive/piped-command loop for the connectionless UDP transport.
+///
+/// There's no socket to lose here, so unlike [`run`] this never spawns a
+/// heartbeat task.
+async fn run_udp(hostname: &str, port: u16, command: Option<&str>, timeout: u64) -> GenericResult {
+    if unix_socket_path(hostname).is_some() {
+        anyhow::bail!(
+            "Unix domain sockets are not supported with --transport udp; drop --transport \
+             udp to reach a Unix socket over the default TCP/Unix transport instead"
+        );
+    }
+
+    // Bind a wildcard address matching the target's address family so an
+    // IPv6-only host isn't forced through an IPv4-only socket.
+    let target_is_ipv6 = tokio::net::lookup_host((hostname, port))
+        .await?
+        .next()
+        .context("Could not resolve host")?
+        .is_ipv6();
+
+    let bind_addr: (&str, u16) = if target_is_ipv6 {
+        ("::", 0)
+    } else {
+        ("0.0.0.0", 0)
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect((hostname, port)).await?;
+
+    // If a command was passed in from the -c option, process it and exit.
+    if let Some(cmd) = command {
+        for line in cmd.lines() {
+            let response = write_cmd_datagram(&socket, line, timeout).await?;
+
+            if let Some(resp) = response {
+                println!("{}", resp);
+            };
+        }
+
+        return Ok(());
+    }
+
+    // Set up the prompt styling.
+    let default_prompt = format!("{}> ", hostname);
+    let helper = HighlightPrompt {
+        colored_prompt: format!("{}> ", hostname.green()),
+    };
+    let mut rl = rustyline::Editor::new()?;
+    rl.set_history_ignore_space(true);
+    rl.set_helper(Some(helper));
+
+    // Enter the input loop.
+    loop {
+        let read = rl.readline(&default_prompt);
+
+        if read.is_err() {
+            println!("Exiting.");
+            exit(0);
+        }
+
+        let input = read.unwrap();
+
+        rl.add_history_entry(&input)?;
+
+        let response = write_cmd_datagram(&socket, &input, timeout).await?;
+
+        if let Some(resp) = response {
+            println!("{}", resp);
+        };
+    }
+}
+
+/// The canned responses a fresh mock instrument answers with before any
+/// `--responses` seed file is applied.
+fn default_responses() -> HashMap<String, String> {
+    let mut responses = HashMap::new();
+    responses.insert("*IDN?".to_owned(), "MOCKCO,SCPI-SIM,0,1.0".to_owned());
+
+    return responses;
+}
+
+/// Loads `COMMAND=RESPONSE` pairs (one per line, blank lines and `#`
+/// comments ignored) from `path`, overriding any defaults with the same key.
+fn load_responses(path: &Path, responses: &mut HashMap<String, String>) -> anyhow::Result<()> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read response file {}", path.display()))?;
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (command, response) = line
+            .split_once('=')
+            .with_context(|| format!("Malformed response mapping: {}", line))?;
+
+        responses.insert(command.trim().to_owned(), response.trim().to_owned());
+    }
+
+    return Ok(());
+}
+
+/// Answers one client connection: reads newline-terminated commands and,
+/// for anything [`is_query`] considers a query, writes back the mapped
+/// response (or a blank line if the command isn't in `responses`).
+async fn serve_connection(
+    socket: TcpStream,
+    responses: Arc<HashMap<String, String>>,
+) -> anyhow::Result<()> {
+    let (reader, mut writer) = socket.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        let bytes_read = reader.read_line(&mut line).await?;
+
+        // EOF: the client hung up.
+        if bytes_read == 0 {
+            return Ok(());
+        }
+
+        let command = line.trim();
+
+        if command.is_empty() {
+            continue;
+        }
+
+        if is_query(command) {
+            let response = responses.get(command).map(String::as_str).unwrap_or("");
+
+            writer
+                .write_all(format!("{}\n", response).as_bytes())
+                .await?;
+        }
+    }
+}
+
+/// Runs as a mock SCPI instrument: binds `port`, accepts connections, and
+/// answers queries from a static command -> response table seeded from
+/// `responses_path` (falling back to [`default_responses`]).
+async fn run_listener(port: u16, responses_path: Option<&Path>) -> GenericResult {
+    let mut responses = default_responses();
+
+    if let Some(path) = responses_path {
+        load_responses(path, &mut responses)?;
+    }
+
+    let responses = Arc::new(responses);
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+
+    println!("Listening on port {}", port);
+
+    loop {
+        let (socket, peer) = listener.accept().await?;
+        let responses = responses.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = serve_connection(socket, responses).await {
+                eprintln!("Connection from {} ended: {}", peer, err);
+            }
+        });
+    }
+}
+
 #[tokio::main]
 async fn main() -> GenericResult {
     let mut args = Args::parse();
 
     // We're receiving piped or redirected data.
-    if !atty::is(Stdin) {
+    if args.listen.is_none() && !atty::is(Stdin) {
         let lines: Vec<String> = io::stdin().lines().map(|x| x.unwrap()).collect();
 
         args.command = lines.join("\n").into();
     }
 
+    let result = if let Some(port) = args.listen {
+        crate::run_listener(port, args.responses.as_deref()).await
+    } else {
+        let host = args.host.clone().expect("host is required unless --listen is set");
+
+        match args.transport {
+            TransportKind::Tcp => {
+                let tuning = TcpTuning::from(&args);
+                crate::run(
+                    &host,
+                    args.port,
+                    args.command.as_deref(),
+                    args.timeout,
+                    &tuning,
+                )
+                .await
+            }
+            TransportKind::Udp => {
+                crate::run_udp(&host, args.port, args.command.as_deref(), args.timeout).await
+            }
+        }
+    };
+
     // Release mode needs special error handling to not print backtraces for minor errors.
     #[cfg(not(debug_assertions))]
     {
-        let res = crate::run(&args.host, args.port, args.command.as_deref(), args.timeout).await;
-
-        if let Err(ref inner) = res {
+        if let Err(ref inner) = result {
             eprintln!("ERROR: {}", inner.to_string());
             exit(1);
         }
@@ -298,7 +769,7 @@ async fn main() -> GenericResult {
     // Debug mode will pass errors straight to the return so we get a full backtrace.
     #[cfg(debug_assertions)]
     {
-        run(&args.host, args.port, args.command.as_deref(), args.timeout).await?;
+        result?;
     }
 
     return Ok(());
@@ -335,4 +806,77 @@ mod tests {
 
         assert_eq!(response, "123");
     }
+
+    #[test]
+    fn unix_socket_detection() {
+        assert_eq!(unix_socket_path("unix:///tmp/instr.sock"), Some("/tmp/instr.sock"));
+        assert_eq!(unix_socket_path("/tmp/instr.sock"), Some("/tmp/instr.sock"));
+
+        assert_eq!(unix_socket_path("localhost"), None);
+        assert_eq!(unix_socket_path("192.168.1.1"), None);
+    }
+
+    #[tokio::test]
+    async fn datagram_response() {
+        let client = UdpSocket::bind(("127.0.0.1", 0)).await.unwrap();
+        let server = UdpSocket::bind(("127.0.0.1", 0)).await.unwrap();
+
+        client.connect(server.local_addr().unwrap()).await.unwrap();
+
+        let query = tokio::spawn(async move {
+            write_cmd_datagram(&client, "QUERY?", 5)
+                .await
+                .expect("Failed to write test query")
+                .expect("Did not get test query response")
+        });
+
+        let mut buf = [0u8; 64];
+        let (size, peer) = server.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..size], b"QUERY?\n");
+
+        server.send_to(b"123\n", peer).await.unwrap();
+
+        assert_eq!(query.await.unwrap(), "123");
+    }
+
+    #[tokio::test]
+    async fn listener_answers_queries() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut responses = default_responses();
+            responses.insert("MEAS:VOLT?".to_owned(), "5.0".to_owned());
+
+            serve_connection(socket, Arc::new(responses)).await.unwrap();
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+
+        let idn = write_cmd(&mut client, "*IDN?", 5).await.unwrap().unwrap();
+        assert_eq!(idn, "MOCKCO,SCPI-SIM,0,1.0");
+
+        let volt = write_cmd(&mut client, "MEAS:VOLT?", 5)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(volt, "5.0");
+    }
+
+    #[test]
+    fn parses_response_seed_file() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("scpi-test-responses-{}.txt", std::process::id()));
+
+        std::fs::write(&path, "# comment\n*IDN?=TESTCO,UNIT,0,1.0\n\nMEAS:VOLT?=5.0\n").unwrap();
+
+        let mut responses = default_responses();
+        load_responses(&path, &mut responses).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(responses.get("*IDN?").unwrap(), "TESTCO,UNIT,0,1.0");
+        assert_eq!(responses.get("MEAS:VOLT?").unwrap(), "5.0");
+    }
 }